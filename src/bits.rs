@@ -1,136 +1,307 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Byte-packing convention used by [`BitReader`]/[`BitWriter`] to fill
+/// their internal 64-bit cache. MSB-first big-endian (`Be`) is the
+/// on-disk format produced by this crate's own container; the `Le16`/
+/// `Le32` variants exist for interop with formats that pack bits into
+/// 16- or 32-bit little-endian words before reading them MSB-first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitReaderMode {
+    /// Bytes are consumed one at a time, in order.
+    Be,
+    /// Bytes are consumed two at a time, as a little-endian `u16`.
+    Le16,
+    /// Bytes are consumed four at a time, as a little-endian `u32`.
+    Le32,
+}
+
+impl BitReaderMode {
+    /// Number of input bytes that make up one unit in this mode.
+    fn unit_len(self) -> usize {
+        match self {
+            BitReaderMode::Be => 1,
+            BitReaderMode::Le16 => 2,
+            BitReaderMode::Le32 => 4,
+        }
+    }
+}
+
+/// Reads a stream of bits out of a byte slice, MSB of each unit first.
+///
+/// Internally this keeps a 64-bit `cache` topped up from `data` by
+/// [`refill`](Self::refill), so that `read_bits` can serve most calls
+/// with a shift and a mask instead of a bit-at-a-time loop.
 pub struct BitReader<'a> {
     data: &'a [u8],
-    ix: usize,
+
+    /// Index of the next unconsumed byte in `data`.
+    pos: usize,
+
+    /// Bits pulled from `data` but not yet returned, left-aligned: the
+    /// next bit to read is the top bit of `cache`.
+    cache: u64,
+
+    /// Number of valid bits currently held in `cache`.
+    bits_in_cache: u8,
+
+    mode: BitReaderMode,
 }
 
 impl<'a> BitReader<'a> {
     pub fn new(data: &'a [u8]) -> Self {
-        BitReader { data, ix: 0 }
+        Self::with_mode(data, BitReaderMode::Be)
     }
 
-    pub fn read_bit(&mut self) -> Option<bool> {
-        let byte_ix = self.byte_ix();
-
-        if byte_ix < self.data.len() {
-            let byte = self.data[byte_ix];
-            let res = (byte & (1 << self.bit_ix())) > 0;
-            self.ix += 1;
-            Some(res)
-        } else {
-            None
+    pub fn with_mode(data: &'a [u8], mode: BitReaderMode) -> Self {
+        BitReader {
+            data,
+            pos: 0,
+            cache: 0,
+            bits_in_cache: 0,
+            mode,
         }
     }
 
-    pub fn read_bits(&mut self, n: u8) -> Option<u8> {
-        assert!(n <= 8);
+    /// Tops `cache` up with whole units from `data` until the next unit
+    /// would no longer fit in the 64-bit cache (more than 56 bits
+    /// buffered in `Be` mode, more than 48 in `Le16`, more than 32 in
+    /// `Le32`) or the input is exhausted. Once fewer than a
+    /// full unit remains, falls back to pulling one raw byte at a time
+    /// so that trailing, not-quite-a-unit data is never lost.
+    fn refill(&mut self) {
+        let unit_len = self.mode.unit_len();
 
-        let mut res = 0;
-        for _ in 0..n {
-            if let Some(b) = self.read_bit() {
-                res = (res << 1) | if b { 1 } else { 0 };
-            } else {
-                return None;
+        while self.pos < self.data.len() {
+            let have_full_unit = self.data.len() - self.pos >= unit_len;
+            let unit_bits: u8 = if have_full_unit { (unit_len * 8) as u8 } else { 8 };
+
+            // Stop once the next unit wouldn't fit; leave it for the
+            // following call instead of overflowing the cache.
+            if self.bits_in_cache + unit_bits > 64 {
+                break;
             }
-        }
-        Some(res)
-    }
 
-    pub fn read_u32_be(&mut self) -> Option<u32> {
-        let mut bytes = [0; 4];
-        for i in 0..4 {
-            if let Some(byte) = self.read_bits(8) {
-                bytes[i] = byte;
+            let unit: u64 = if !have_full_unit {
+                self.data[self.pos] as u64
             } else {
-                return None;
-            }
+                match self.mode {
+                    BitReaderMode::Be => self.data[self.pos] as u64,
+                    BitReaderMode::Le16 => {
+                        let bytes = [self.data[self.pos], self.data[self.pos + 1]];
+                        u16::from_le_bytes(bytes) as u64
+                    }
+                    BitReaderMode::Le32 => {
+                        let bytes = [
+                            self.data[self.pos],
+                            self.data[self.pos + 1],
+                            self.data[self.pos + 2],
+                            self.data[self.pos + 3],
+                        ];
+                        u32::from_le_bytes(bytes) as u64
+                    }
+                }
+            };
+
+            self.cache |= unit << (64 - self.bits_in_cache - unit_bits);
+            self.bits_in_cache += unit_bits;
+            self.pos += if have_full_unit { unit_len } else { 1 };
         }
-        Some(u32::from_be_bytes(bytes))
     }
 
+    pub fn read_bit(&mut self) -> Option<bool> {
+        self.read_bits(1).map(|b| b != 0)
+    }
+
+    /// Reads `n` (up to 32) bits MSB-first, returned right-aligned in
+    /// a `u32`.
+    pub fn read_bits(&mut self, n: u8) -> Option<u32> {
+        assert!(n <= 32);
+
+        if n == 0 {
+            return Some(0);
+        }
+
+        if self.bits_in_cache < n {
+            self.refill();
+        }
+
+        if self.bits_in_cache < n {
+            return None;
+        }
 
-    fn byte_ix(&self) -> usize {
-        self.ix / 8
+        let res = (self.cache >> (64 - n)) as u32;
+        self.cache <<= n;
+        self.bits_in_cache -= n;
+        Some(res)
     }
 
-    /// This returns a bit index in current byte using standard
-    /// indexing convention: i.e. 0 is the least significant bit, 7 is
-    /// the most significant bit.
-    fn bit_ix(&self) -> usize {
-        7 - self.ix % 8
+    pub fn read_u32_be(&mut self) -> Option<u32> {
+        self.read_bits(32)
     }
 }
 
-/// BitWriter for dynamic Vector-based buffers.
+/// Writes a stream of bits into a growable buffer, MSB of each unit
+/// first. Mirrors [`BitReader`]'s cache-based design, and must be
+/// constructed with the same [`BitReaderMode`] as the reader that will
+/// decode its output.
 pub struct BitWriter {
     /// Underlying buffer.
     buf: Vec<u8>,
 
-    /// Index pointing to the current bit. Starts from the leftmost
-    /// byte. Goes from the most signicant bit to the least
-    /// significant bit of each byte.
-    ix: usize,
+    /// Bits queued but not yet flushed to `buf`, left-aligned: the
+    /// next bit to flush is the top bit of `cache`.
+    cache: u64,
+
+    /// Number of valid bits currently held in `cache`.
+    bits_in_cache: u8,
+
+    mode: BitReaderMode,
+}
+
+impl Default for BitWriter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl BitWriter {
     pub fn new() -> Self {
+        Self::with_mode(BitReaderMode::Be)
+    }
+
+    pub fn with_mode(mode: BitReaderMode) -> Self {
         Self {
             buf: vec![],
-            ix: 0,
+            cache: 0,
+            bits_in_cache: 0,
+            mode,
         }
     }
 
     /// Passes ownership of the internally built buffer to be used
-    /// outside.
-    pub fn dump(self) -> Vec<u8> {
+    /// outside, flushing any bits still queued in the cache. The
+    /// remainder is padded with zero bits up to a whole unit and
+    /// emitted in `mode`'s byte order, same as `drain_units`, so a
+    /// `BitReader` in the same mode reads it back correctly.
+    pub fn dump(mut self) -> Vec<u8> {
+        if self.bits_in_cache > 0 {
+            let unit_bits = (self.mode.unit_len() as u8) * 8;
+            self.bits_in_cache = unit_bits;
+            self.drain_units();
+        }
         self.buf
     }
 
-    /// Write u32 big endian style.
+    /// Write u32 big endian style (subject to the writer's `mode`).
     pub fn write_u32_be(&mut self, x: u32) {
-        for &b in x.to_be_bytes().iter() {
-            self.write_bits(8, b);
-        }
+        self.write_bits(32, x);
     }
 
     /// Write a single bit passed as `bool`.
     pub fn write_bit(&mut self, bit: bool) {
-        if self.is_full() {
-            self.buf.push(0);
+        self.write_bits(1, bit as u32);
+    }
+
+    /// Write up to 32 bits, taken from the low `num_of_bits` bits of
+    /// `data`, MSB first.
+    pub fn write_bits(&mut self, num_of_bits: u8, data: u32) {
+        assert!(num_of_bits <= 32);
+
+        if num_of_bits == 0 {
+            return;
         }
 
-        let byte_ix = self.byte_ix();
-        if bit {
-            self.buf[byte_ix] |= 1 << self.bit_ix();
+        let mask = if num_of_bits == 32 {
+            u32::MAX
         } else {
-            self.buf[byte_ix] &= !(1 << self.bit_ix());
-        }
+            (1u32 << num_of_bits) - 1
+        };
+        let bits = (data & mask) as u64;
+        self.cache |= bits << (64 - self.bits_in_cache - num_of_bits);
+        self.bits_in_cache += num_of_bits;
+        self.drain_units();
+    }
 
-        self.ix += 1;
+    /// Flushes whole units from the top of `cache` into `buf`, in the
+    /// byte order `mode` calls for.
+    fn drain_units(&mut self) {
+        let unit_bits = (self.mode.unit_len() as u8) * 8;
+        while self.bits_in_cache >= unit_bits {
+            let unit = self.cache >> (64 - unit_bits);
+            match self.mode {
+                BitReaderMode::Be => self.buf.push(unit as u8),
+                BitReaderMode::Le16 => self.buf.extend_from_slice(&(unit as u16).to_le_bytes()),
+                BitReaderMode::Le32 => self.buf.extend_from_slice(&(unit as u32).to_le_bytes()),
+            }
+            self.cache <<= unit_bits;
+            self.bits_in_cache -= unit_bits;
+        }
     }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
 
-    /// Write less than 8 bits passed in `u8`.
-    pub fn write_bits(&mut self, num_of_bits: u8, data: u8) {
-        assert!(num_of_bits <= 8);
-        for offset in (0..num_of_bits).rev() {
-            let bit = (data >> offset) & 1;
-            self.write_bit(bit == 1);
+    /// Writes `bits` (each a 0/1 `u8`) with `mode`, then reads them
+    /// back with a `BitReader` in the same mode, and checks they
+    /// match. Covers both whole-unit and partial-final-unit payloads.
+    fn round_trip(mode: BitReaderMode, bits: &[u8]) {
+        let mut writer = BitWriter::with_mode(mode);
+        for &bit in bits {
+            writer.write_bit(bit != 0);
         }
+        let buf = writer.dump();
+
+        let mut reader = BitReader::with_mode(&buf, mode);
+        for (i, &bit) in bits.iter().enumerate() {
+            let got = reader.read_bit().unwrap_or_else(|| {
+                panic!("{:?}: ran out of bits at index {}", mode, i)
+            });
+            assert_eq!(got, bit != 0, "{:?}: mismatch at bit {}", mode, i);
+        }
+    }
+
+    #[test]
+    fn round_trip_be_whole_and_partial_unit() {
+        round_trip(BitReaderMode::Be, &[1, 0, 1, 1, 0, 0, 1, 0]); // one whole unit
+        round_trip(BitReaderMode::Be, &[1, 0, 1, 1, 0]); // partial final unit
     }
 
-    /// Index of the current byte.
-    fn byte_ix(&self) -> usize {
-        self.ix / 8
+    #[test]
+    fn round_trip_le16_whole_and_partial_unit() {
+        let whole: Vec<u8> = (0..16).map(|i| i % 2).collect();
+        round_trip(BitReaderMode::Le16, &whole);
+        round_trip(BitReaderMode::Le16, &whole[..11]); // partial final unit
     }
 
-    /// This returns a bit index in current byte using standard
-    /// indexing convention: i.e. 0 is the least significant bit, 7 is
-    /// the most significant bit.
-    fn bit_ix(&self) -> usize {
-        7 - self.ix % 8
+    #[test]
+    fn round_trip_le32_whole_and_partial_unit() {
+        let whole: Vec<u8> = (0..32).map(|i| i % 2).collect();
+        round_trip(BitReaderMode::Le32, &whole);
+        round_trip(BitReaderMode::Le32, &whole[..28]); // partial final unit
     }
 
-    /// If the buffer is full and there is no space to write anything.
-    fn is_full(&self) -> bool {
-        self.ix >= self.buf.len() * 8
+    #[quickcheck]
+    fn read_bits_round_trips_per_mode(mode_tag: u8, value: u32, len_tag: u8) -> bool {
+        let mode = match mode_tag % 3 {
+            0 => BitReaderMode::Be,
+            1 => BitReaderMode::Le16,
+            _ => BitReaderMode::Le32,
+        };
+        let len = 1 + (len_tag % 32);
+        let masked = if len == 32 {
+            value
+        } else {
+            value & ((1u32 << len) - 1)
+        };
+
+        let mut writer = BitWriter::with_mode(mode);
+        writer.write_bits(len, masked);
+        let buf = writer.dump();
+
+        let mut reader = BitReader::with_mode(&buf, mode);
+        reader.read_bits(len) == Some(masked)
     }
 }
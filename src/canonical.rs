@@ -0,0 +1,233 @@
+//! Canonical Huffman code construction and the on-wire code-length
+//! header that replaces the old full-trie serialization.
+//!
+//! Given just the bit-length of each symbol's code (its depth in the
+//! original trie), canonical assignment rebuilds an equivalent prefix
+//! code deterministically: symbols are ordered by `(length, byte
+//! value)`, and codes are assigned starting from zero, incrementing
+//! after each symbol and shifting left by one whenever the length
+//! grows. The header stores the full 256-entry length table as
+//! run-length `(length, run)` pairs of 24 bits each after a 16-bit run
+//! count; this is not smaller than the ~9-bits-per-leaf trie encoding
+//! it replaces (a sparse table costs about two runs per present
+//! symbol). Canonical codes let the decoder use a table-driven lookup
+//! instead of walking a tree one bit at a time.
+
+use alloc::vec::Vec;
+
+use crate::bits::{BitReader, BitWriter};
+use crate::io;
+
+/// Longest code length this module's header format and decode table
+/// support. Headers claiming a longer code are rejected as malformed.
+///
+/// Capped at 31, one short of the `u32` accumulators used by
+/// [`assign_codes`] and [`DecodeTable`]: a length-32 code would need
+/// the code space to reach `2^32`, which doesn't fit a `u32` and would
+/// overflow those accumulators on a Kraft-valid header.
+pub(crate) const MAX_CODE_LEN: usize = 31;
+
+/// A symbol's canonical code: the low `len` bits of `code`, MSB first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Code {
+    pub code: u32,
+    pub len: u8,
+}
+
+/// Assigns canonical codes to every symbol with a non-zero entry in
+/// `lengths` (indexed by byte value; 0 means "absent").
+pub fn assign_codes(lengths: &[u8; 256]) -> [Option<Code>; 256] {
+    let mut order: Vec<usize> = (0..256).filter(|&b| lengths[b] > 0).collect();
+    order.sort_by_key(|&b| (lengths[b], b as u8));
+
+    let mut table = [None; 256];
+    let mut code: u32 = 0;
+    let mut prev_len: u8 = 0;
+    for b in order {
+        let len = lengths[b];
+        code <<= len - prev_len;
+        table[b] = Some(Code { code, len });
+        code += 1;
+        prev_len = len;
+    }
+    table
+}
+
+/// Serializes `lengths` as a run-length-encoded sequence of `(length,
+/// run_len)` pairs covering all 256 entries.
+pub fn write_length_header(writer: &mut BitWriter, lengths: &[u8; 256]) {
+    let mut runs: Vec<(u8, u16)> = Vec::new();
+    let mut i = 0;
+    while i < 256 {
+        let value = lengths[i];
+        let mut run = 1u16;
+        while i + (run as usize) < 256 && lengths[i + run as usize] == value {
+            run += 1;
+        }
+        runs.push((value, run));
+        i += run as usize;
+    }
+
+    writer.write_bits(16, runs.len() as u32);
+    for (value, run) in runs {
+        writer.write_bits(8, value as u32);
+        writer.write_bits(16, (run - 1) as u32);
+    }
+}
+
+/// Reverses [`write_length_header`], then validates the result against
+/// the Kraft inequality. Fails with `InvalidData` if the header is
+/// malformed, or `UnexpectedEof` if `reader` runs out of bits first.
+pub fn read_length_header(reader: &mut BitReader) -> io::Result<[u8; 256]> {
+    let num_runs = reader.read_bits(16).ok_or_else(io::unexpected_eof)?;
+
+    let mut lengths = [0u8; 256];
+    let mut i = 0usize;
+    for _ in 0..num_runs {
+        let value = reader.read_bits(8).ok_or_else(io::unexpected_eof)? as u8;
+        let run = reader.read_bits(16).ok_or_else(io::unexpected_eof)? as usize + 1;
+        if i + run > 256 {
+            return Err(io::invalid_data(
+                "read_length_header: run overflows the length table",
+            ));
+        }
+        for slot in &mut lengths[i..i + run] {
+            *slot = value;
+        }
+        i += run;
+    }
+    if i != 256 {
+        return Err(io::invalid_data(
+            "read_length_header: runs don't cover all 256 symbols",
+        ));
+    }
+    if lengths.iter().any(|&len| len as usize > MAX_CODE_LEN) {
+        return Err(io::invalid_data(
+            "read_length_header: code length exceeds MAX_CODE_LEN",
+        ));
+    }
+    if !check_kraft(&lengths) {
+        return Err(io::invalid_data(
+            "read_length_header: code lengths violate the Kraft inequality",
+        ));
+    }
+
+    Ok(lengths)
+}
+
+/// Whether `lengths` (0 = symbol absent) describes a valid prefix
+/// code, i.e. satisfies the Kraft equality `sum(2^-len) == 1` over
+/// present symbols. A lone present symbol is a degenerate case (its
+/// one-bit code is assigned but never actually read) and always
+/// passes.
+pub(crate) fn check_kraft(lengths: &[u8; 256]) -> bool {
+    let present: Vec<u8> = lengths.iter().copied().filter(|&len| len > 0).collect();
+
+    if present.len() <= 1 {
+        return true;
+    }
+
+    let max_len = *present.iter().max().unwrap();
+    if max_len == 0 || max_len as usize > MAX_CODE_LEN {
+        return false;
+    }
+
+    let denom = 1u64 << max_len;
+    let sum: u64 = present.iter().map(|&len| denom >> len).sum();
+    sum == denom
+}
+
+/// A table-driven canonical Huffman decoder: given the bit-lengths of
+/// present symbols, it can recover a symbol from its code without
+/// walking a tree one bit at a time.
+pub struct DecodeTable {
+    /// Present symbols, in canonical order (sorted by `(len, byte)`).
+    symbols: Vec<u8>,
+    /// `first_code[len]`: the first code assigned to a length-`len`
+    /// symbol.
+    first_code: [u32; MAX_CODE_LEN + 1],
+    /// `first_index[len]`: index into `symbols` where the length-`len`
+    /// run starts.
+    first_index: [usize; MAX_CODE_LEN + 1],
+    /// Number of symbols with exactly length `len`.
+    count: [usize; MAX_CODE_LEN + 1],
+}
+
+impl DecodeTable {
+    pub fn new(lengths: &[u8; 256]) -> Self {
+        let mut symbols: Vec<(u8, u8)> = (0..256)
+            .filter_map(|b| {
+                let len = lengths[b];
+                if len > 0 {
+                    Some((b as u8, len))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        symbols.sort_by_key(|&(b, len)| (len, b));
+
+        let mut count = [0usize; MAX_CODE_LEN + 1];
+        for &(_, len) in &symbols {
+            count[len as usize] += 1;
+        }
+
+        let mut first_code = [0u32; MAX_CODE_LEN + 1];
+        let mut first_index = [0usize; MAX_CODE_LEN + 1];
+        let mut code = 0u32;
+        let mut index = 0usize;
+        for len in 1..=MAX_CODE_LEN {
+            first_code[len] = code;
+            first_index[len] = index;
+            code = (code + count[len] as u32) << 1;
+            index += count[len];
+        }
+
+        DecodeTable {
+            symbols: symbols.into_iter().map(|(b, _)| b).collect(),
+            first_code,
+            first_index,
+            count,
+        }
+    }
+
+    /// Decodes one symbol from `reader`, growing a candidate code one
+    /// bit at a time until it falls within a known length's range.
+    /// Fails with `UnexpectedEof` if `reader` runs out of bits first, or
+    /// `InvalidData` if no known length ever matches (a corrupt table
+    /// or payload).
+    pub fn decode(&self, reader: &mut BitReader) -> io::Result<u8> {
+        let mut code = 0u32;
+        for len in 1..=MAX_CODE_LEN {
+            let bit = reader.read_bit().ok_or_else(io::unexpected_eof)?;
+            code = (code << 1) | bit as u32;
+
+            if self.count[len] > 0 && code >= self.first_code[len] {
+                let offset = (code - self.first_code[len]) as usize;
+                if offset < self.count[len] {
+                    return Ok(self.symbols[self.first_index[len] + offset]);
+                }
+            }
+        }
+        Err(io::invalid_data(
+            "DecodeTable::decode: code doesn't match any known length",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_kraft_rejects_length_32_codes() {
+        // A length-32 code would need the code space to reach 2^32,
+        // overflowing the u32 accumulators in `assign_codes` and
+        // `DecodeTable`; `MAX_CODE_LEN` caps lengths at 31 to rule it
+        // out before either ever sees one.
+        let mut lengths = [0u8; 256];
+        lengths[0] = 32;
+        lengths[1] = 32;
+        assert!(!check_kraft(&lengths));
+    }
+}
@@ -0,0 +1,242 @@
+//! The actual Huffman trie building, canonical-code (de)serialization
+//! and compress/extract entry points. Kept separate from `main.rs` so
+//! it compiles under `no_std` + `alloc`.
+
+use alloc::boxed::Box;
+use alloc::collections::BinaryHeap;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+use log::debug;
+
+use crate::bits::{BitReader, BitWriter};
+use crate::canonical::{self, Code, DecodeTable};
+use crate::io;
+
+#[derive(Debug)]
+struct Node {
+    byte: u8,
+    freq: usize,
+    children: Option<Box<(Node, Node)>>,
+}
+
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        self.freq == other.freq
+    }
+}
+
+impl Eq for Node {}
+
+impl Ord for Node {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.freq.cmp(&other.freq).reverse()
+    }
+}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Node {
+    fn leaf(byte: u8, freq: usize) -> Self {
+        Node {
+            byte,
+            freq,
+            children: None,
+        }
+    }
+
+    fn merge(l: Node, r: Node) -> Node {
+        Node {
+            byte: 0,
+            freq: l.freq + r.freq,
+            children: Some(Box::new((l, r))),
+        }
+    }
+}
+
+fn freq_table(data: &[u8]) -> [usize; 256] {
+    let mut counter = [0; 256];
+    for &b in data {
+        counter[b as usize] += 1;
+    }
+    counter
+}
+
+fn build_trie(freq_table: [usize; 256]) -> Node {
+    let mut heap = BinaryHeap::with_capacity(256);
+    for (b, &freq) in freq_table.iter().enumerate() {
+        if freq > 0 {
+            heap.push(Node::leaf(b as u8, freq));
+        }
+    }
+
+    while heap.len() > 1 {
+        // Merge two smallest nodes and push the result back.
+        let l = heap.pop().unwrap();
+        let r = heap.pop().unwrap();
+        heap.push(Node::merge(l, r));
+    }
+
+    heap.pop().unwrap_or(Node::leaf(0, 0))
+}
+
+/// Derives each symbol's canonical code length from its depth in
+/// `trie`. A lone symbol has depth 0 in a single-leaf trie, but every
+/// present symbol still needs a code, so its length is bumped to 1.
+fn code_lengths(trie: &Node) -> [u8; 256] {
+    fn go(node: &Node, depth: u8, lengths: &mut [u8; 256]) {
+        match &node.children {
+            None => {
+                if node.freq > 0 {
+                    lengths[node.byte as usize] = depth.max(1);
+                }
+            }
+            Some(children) => {
+                go(&children.0, depth + 1, lengths);
+                go(&children.1, depth + 1, lengths);
+            }
+        }
+    }
+
+    let mut lengths = [0u8; 256];
+    go(trie, 0, &mut lengths);
+    lengths
+}
+
+fn write_encoded_data(writer: &mut BitWriter, data: &[u8], codes: &[Option<Code>; 256]) {
+    writer.write_u32_be(data.len() as u32);
+    debug!("write_encoded_data: writing size of data: {}", data.len());
+    for &d in data {
+        let entry = codes[d as usize].expect("write_encoded_data: symbol has no code");
+        debug!(
+            "write_encoded_data: writing byte {} using code {:?}",
+            d, entry
+        );
+        writer.write_bits(entry.len, entry.code);
+    }
+}
+
+fn read_decoded_data(reader: &mut BitReader, table: &DecodeTable) -> io::Result<Vec<u8>> {
+    let size = reader.read_u32_be().ok_or_else(io::unexpected_eof)?;
+
+    debug!("read_decoded_data: reading size of data: {}", size);
+
+    (0..size).map(|_| table.decode(reader)).collect()
+}
+
+/// Default cap on code length used by [`compress`]; well below the
+/// 31-bit maximum that the canonical decoder ([`DecodeTable`]) accepts,
+/// while still being generous for any realistic byte distribution.
+pub const DEFAULT_MAX_CODE_LEN: u8 = 15;
+
+/// Huffman-codes a single block: a canonical code-length header
+/// followed by the coded payload. Code lengths are capped at
+/// `max_len` bits, falling back to [`crate::package_merge`] whenever
+/// the trie's natural depths would exceed it; that fallback fails with
+/// `InvalidData` if even `max_len` bits aren't enough to give every
+/// distinct byte in `data` its own code (i.e. `2^max_len <
+/// distinct_symbol_count`). A lone distinct byte always gets a 1-bit
+/// code, even when `max_len` is 0. Used by [`crate::stream`] to code one frame
+/// at a time; [`compress`] and [`compress_with_limit`] are thin,
+/// whole-buffer wrappers around it.
+pub(crate) fn compress_block(data: &[u8], max_len: u8) -> io::Result<Vec<u8>> {
+    let freqs = freq_table(data);
+    let trie = build_trie(freqs);
+    let mut lengths = code_lengths(&trie);
+
+    let present: Vec<(u8, usize)> = freqs
+        .iter()
+        .enumerate()
+        .filter_map(|(b, &f)| if f > 0 { Some((b as u8, f)) } else { None })
+        .collect();
+
+    if present.len() >= 2 && lengths.iter().any(|&len| len > max_len) {
+        lengths = crate::package_merge::limited_lengths(&present, max_len)?;
+    }
+
+    let codes = canonical::assign_codes(&lengths);
+
+    let mut writer = BitWriter::new();
+    canonical::write_length_header(&mut writer, &lengths);
+    write_encoded_data(&mut writer, data, &codes);
+    Ok(writer.dump())
+}
+
+/// Reverses [`compress_block`]. Fails with `InvalidData`/`UnexpectedEof`
+/// instead of panicking if `data` is truncated or malformed.
+pub(crate) fn extract_block(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut reader = BitReader::new(data);
+    let lengths = canonical::read_length_header(&mut reader)?;
+    let table = DecodeTable::new(&lengths);
+    read_decoded_data(&mut reader, &table)
+}
+
+/// Compresses `data` into a self-contained, block-framed buffer (see
+/// [`crate::stream`]), using [`DEFAULT_MAX_CODE_LEN`] as the code
+/// length cap.
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    compress_with_limit(data, DEFAULT_MAX_CODE_LEN)
+}
+
+/// Like [`compress`], but lets the caller cap code lengths at
+/// `max_len` bits instead of [`DEFAULT_MAX_CODE_LEN`].
+///
+/// Panics if `max_len` is too small to give every distinct byte value
+/// in any [`crate::stream::BLOCK_SIZE`]-sized chunk of `data` its own
+/// code, i.e. unless `2^max_len >= distinct_symbol_count` holds for
+/// every chunk; use [`crate::stream::compress_stream_with_limit`]
+/// directly for a fallible version of this precondition.
+pub fn compress_with_limit(data: &[u8], max_len: u8) -> Vec<u8> {
+    let mut out = Vec::new();
+    crate::stream::compress_stream_with_limit(data, &mut out, max_len)
+        .expect("compress_with_limit: max_len too small for this input's distinct symbol count");
+    out
+}
+
+/// Reverses [`compress`]/[`compress_with_limit`]. Panics if `data` isn't
+/// valid output of those functions; use [`crate::stream::extract_stream`]
+/// directly for a fallible decode of untrusted input.
+pub fn extract(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    crate::stream::extract_stream(data, &mut out).expect("extract: malformed or truncated input");
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[quickcheck]
+    fn test_encode_decode_identity(bytes: Vec<u8>) -> bool {
+        let compressed = compress(&bytes[..]);
+        let extracted = extract(&compressed[..]);
+        bytes == extracted
+    }
+
+    #[test]
+    fn extract_block_reports_truncated_payload_instead_of_panicking() {
+        let payload = compress_block(b"hello, world", DEFAULT_MAX_CODE_LEN).unwrap();
+        assert!(extract_block(&payload[..payload.len() / 2]).is_err());
+    }
+
+    #[test]
+    fn extract_block_reports_corrupt_length_header_instead_of_panicking() {
+        let mut payload = compress_block(b"hello, world", DEFAULT_MAX_CODE_LEN).unwrap();
+        // The run count lives in the header's first two bytes; blowing it
+        // up past what the rest of the payload can possibly cover should
+        // be rejected, not panic while filling the length table.
+        payload[0] = 0xff;
+        payload[1] = 0xff;
+        assert!(extract_block(&payload).is_err());
+    }
+
+    #[test]
+    fn compress_block_reports_max_len_too_small_instead_of_panicking() {
+        let data: Vec<u8> = (0..20).collect();
+        assert!(compress_block(&data, 4).is_err());
+    }
+}
@@ -0,0 +1,134 @@
+//! Minimal `Read`/`Write` abstraction so the codec in this crate can be
+//! built with or without `std`.
+//!
+//! With the `std` feature enabled (the default) this module is just a
+//! re-export of the standard library traits. With `std` disabled it
+//! provides a small subset of the same API, sufficient for the
+//! in-memory and streaming codec, backed only by `core`/`alloc`.
+
+#[cfg(feature = "std")]
+pub use std::io::{Error, Read, Result, Write};
+
+#[cfg(feature = "std")]
+pub(crate) fn unexpected_eof() -> Error {
+    Error::new(std::io::ErrorKind::UnexpectedEof, "unexpected end of file")
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn invalid_data(msg: &'static str) -> Error {
+    Error::new(std::io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(not(feature = "std"))]
+mod no_std_io {
+    use alloc::vec::Vec;
+    use core::fmt;
+
+    /// Errors produced by the `no_std` `Read`/`Write` shims.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Error {
+        /// The source ran out of data before a read could be satisfied.
+        UnexpectedEof,
+        /// The sink could not accept any more data.
+        WriteZero,
+        /// The data being read is malformed (bad magic, bad version,
+        /// ...).
+        InvalidData(&'static str),
+    }
+
+    impl fmt::Display for Error {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                Error::UnexpectedEof => write!(f, "unexpected end of file"),
+                Error::WriteZero => write!(f, "failed to write whole buffer"),
+                Error::InvalidData(msg) => write!(f, "{}", msg),
+            }
+        }
+    }
+
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    /// A `no_std` stand-in for `std::io::Read`, scoped to what the
+    /// codec needs.
+    pub trait Read {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+
+        fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<()> {
+            while !buf.is_empty() {
+                match self.read(buf)? {
+                    0 => return Err(Error::UnexpectedEof),
+                    n => buf = &mut buf[n..],
+                }
+            }
+            Ok(())
+        }
+    }
+
+    /// A `no_std` stand-in for `std::io::Write`, scoped to what the
+    /// codec needs.
+    pub trait Write {
+        fn write(&mut self, buf: &[u8]) -> Result<usize>;
+        fn flush(&mut self) -> Result<()>;
+
+        fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+            while !buf.is_empty() {
+                match self.write(buf)? {
+                    0 => return Err(Error::WriteZero),
+                    n => buf = &buf[n..],
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl Read for &[u8] {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            let n = buf.len().min(self.len());
+            let (head, tail) = self.split_at(n);
+            buf[..n].copy_from_slice(head);
+            *self = tail;
+            Ok(n)
+        }
+    }
+
+    impl<R: ?Sized + Read> Read for &mut R {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            (**self).read(buf)
+        }
+    }
+
+    impl<W: ?Sized + Write> Write for &mut W {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            (**self).write(buf)
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            (**self).flush()
+        }
+    }
+
+    impl Write for Vec<u8> {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            self.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    pub(crate) fn unexpected_eof() -> Error {
+        Error::UnexpectedEof
+    }
+
+    pub(crate) fn invalid_data(msg: &'static str) -> Error {
+        Error::InvalidData(msg)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+pub use no_std_io::{Error, Read, Result, Write};
+
+#[cfg(not(feature = "std"))]
+pub(crate) use no_std_io::{invalid_data, unexpected_eof};
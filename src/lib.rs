@@ -0,0 +1,28 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! A small Huffman coding library.
+//!
+//! The codec (`compress`/`extract`, the trie builder and the bit-level
+//! readers/writers in [`bits`]) only depends on `core`/`alloc`, so it
+//! can run in `no_std` environments such as kernels, firmware or WASM
+//! when built with `default-features = false`. The `std` feature (on
+//! by default) additionally builds the `huffman` CLI binary in
+//! `main.rs`.
+
+extern crate alloc;
+
+#[cfg(test)]
+extern crate quickcheck;
+#[cfg(test)]
+#[macro_use(quickcheck)]
+extern crate quickcheck_macros;
+
+pub mod bits;
+mod canonical;
+mod codec;
+pub mod io;
+mod package_merge;
+pub mod stream;
+
+pub use codec::{compress, compress_with_limit, extract, DEFAULT_MAX_CODE_LEN};
+pub use stream::{compress_stream, compress_stream_with_limit, extract_stream, BLOCK_SIZE};
@@ -0,0 +1,138 @@
+//! Length-limited Huffman code generation via the package-merge
+//! algorithm (Larmore & Hirschberg).
+//!
+//! [`crate::codec`]'s ordinary trie depths already give the
+//! *unconstrained* minimum-redundancy lengths, but nothing stops a
+//! skewed frequency table from producing a code longer than a
+//! table-driven decoder can handle. `limited_lengths` instead finds
+//! the minimum-redundancy code subject to `len <= max_len`, by
+//! treating each symbol as a "coin" of weight equal to its frequency
+//! and face value `2^-len`, then repeatedly pairing up the cheapest
+//! coins into "packages" that stand in for a single coin one bit
+//! shorter.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::io;
+
+#[derive(Clone)]
+struct Item {
+    weight: u64,
+    symbols: Vec<u8>,
+}
+
+/// Pairs up adjacent (by weight) items into packages, each combining
+/// the weight and symbols of the pair. An odd item out at the end has
+/// no partner this round and is dropped.
+fn package(items: &[Item]) -> Vec<Item> {
+    items
+        .chunks_exact(2)
+        .map(|pair| {
+            let mut symbols = pair[0].symbols.clone();
+            symbols.extend(pair[1].symbols.iter().copied());
+            Item {
+                weight: pair[0].weight + pair[1].weight,
+                symbols,
+            }
+        })
+        .collect()
+}
+
+/// Computes canonical code lengths for `symbols` (byte value, weight)
+/// such that no length exceeds `max_len`. Requires at least two
+/// symbols (an internal invariant upheld by every caller in this
+/// crate). `max_len` is caller-supplied, though, and a valid
+/// length-limited prefix code only exists when `max_len >= 1` and
+/// `2^max_len >= symbols.len()`; failing either returns `InvalidData`
+/// instead of panicking, since `max_len` ultimately comes from the
+/// public `compress_with_limit`/`compress_stream_with_limit` API.
+pub fn limited_lengths(symbols: &[(u8, usize)], max_len: u8) -> io::Result<[u8; 256]> {
+    let n = symbols.len();
+    assert!(n >= 2, "limited_lengths: need at least two symbols");
+    if max_len < 1 {
+        return Err(io::invalid_data(
+            "limited_lengths: max_len must be at least 1",
+        ));
+    }
+    if 1u64.checked_shl(max_len as u32).unwrap_or(u64::MAX) < n as u64 {
+        return Err(io::invalid_data(
+            "limited_lengths: max_len too small to encode this many distinct symbols",
+        ));
+    }
+
+    let mut originals: Vec<Item> = symbols
+        .iter()
+        .map(|&(b, freq)| Item {
+            weight: freq as u64,
+            symbols: vec![b],
+        })
+        .collect();
+    originals.sort_by_key(|it| it.weight);
+
+    // `current` starts as the coin list for the smallest denomination,
+    // 2^-max_len; each round packages it into coins of the next larger
+    // denomination, ending with the list for 2^-1.
+    let mut current = originals.clone();
+    for _level in 2..=max_len {
+        let mut merged = package(&current);
+        merged.extend(originals.iter().cloned());
+        merged.sort_by_key(|it| it.weight);
+        current = merged;
+    }
+
+    // The 2n-2 cheapest items in the final list are exactly the ones that
+    // minimize total weighted length subject to the length cap; each
+    // symbol's code length is how many of them it appears in.
+    current.sort_by_key(|it| it.weight);
+    let take = (2 * n - 2).min(current.len());
+
+    let mut lengths = [0u8; 256];
+    for item in &current[..take] {
+        for &b in &item.symbols {
+            lengths[b as usize] += 1;
+        }
+    }
+
+    Ok(lengths)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::canonical::check_kraft;
+
+    const MAX_LEN: u8 = 10;
+
+    #[quickcheck]
+    fn lengths_respect_limit_and_kraft(freqs: Vec<u8>) -> quickcheck::TestResult {
+        let mut table = [0usize; 256];
+        for &b in &freqs {
+            table[b as usize] += 1;
+        }
+
+        let present: Vec<(u8, usize)> = table
+            .iter()
+            .enumerate()
+            .filter_map(|(b, &f)| if f > 0 { Some((b as u8, f)) } else { None })
+            .collect();
+
+        if present.len() < 2 {
+            return quickcheck::TestResult::discard();
+        }
+
+        let lengths = limited_lengths(&present, MAX_LEN).unwrap();
+
+        let within_limit = present
+            .iter()
+            .all(|&(b, _)| (1..=MAX_LEN).contains(&lengths[b as usize]));
+
+        quickcheck::TestResult::from_bool(within_limit && check_kraft(&lengths))
+    }
+
+    #[test]
+    fn max_len_too_small_reports_error_instead_of_panicking() {
+        let present: Vec<(u8, usize)> = (0..20).map(|b| (b, 1usize)).collect();
+        assert!(limited_lengths(&present, 4).is_err());
+    }
+}
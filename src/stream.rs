@@ -0,0 +1,103 @@
+//! Streaming, block-framed wrapper around the codec in [`crate::codec`].
+//!
+//! Unlike the whole-buffer `compress`/`extract`, these process input
+//! in fixed-size blocks ([`BLOCK_SIZE`] bytes), each written as an
+//! independent frame: a magic/version header, the frame's payload
+//! length, and the payload itself (that block's canonical code-length
+//! table followed by its Huffman-coded bytes). The decoder only ever
+//! needs one block's worth of memory at a time, so input of unbounded
+//! size can be processed with bounded memory.
+
+use alloc::vec;
+
+use crate::codec::{self, DEFAULT_MAX_CODE_LEN};
+use crate::io::{self, Read, Write};
+
+/// Bytes of raw input Huffman-coded per frame.
+pub const BLOCK_SIZE: usize = 64 * 1024;
+
+const MAGIC: [u8; 2] = *b"HF";
+const VERSION: u8 = 1;
+
+/// `magic (2 bytes) + version (1 byte) + payload length (4 bytes)`.
+const FRAME_HEADER_LEN: usize = 7;
+
+/// Compresses `src` into `dst` as a sequence of block frames, using
+/// [`DEFAULT_MAX_CODE_LEN`] as the per-block code length cap.
+pub fn compress_stream<R: Read, W: Write>(src: R, dst: W) -> io::Result<()> {
+    compress_stream_with_limit(src, dst, DEFAULT_MAX_CODE_LEN)
+}
+
+/// Like [`compress_stream`], but lets the caller cap code lengths at
+/// `max_len` bits per block instead of [`DEFAULT_MAX_CODE_LEN`].
+///
+/// Fails with `InvalidData` unless `2^max_len >= distinct_symbol_count`
+/// holds for every block, since otherwise `max_len` bits aren't enough
+/// to give each distinct byte its own code.
+pub fn compress_stream_with_limit<R: Read, W: Write>(
+    mut src: R,
+    mut dst: W,
+    max_len: u8,
+) -> io::Result<()> {
+    let mut block = vec![0u8; BLOCK_SIZE];
+    loop {
+        let n = read_fill(&mut src, &mut block)?;
+        if n == 0 {
+            break;
+        }
+
+        let payload = codec::compress_block(&block[..n], max_len)?;
+
+        dst.write_all(&MAGIC)?;
+        dst.write_all(&[VERSION])?;
+        dst.write_all(&(payload.len() as u32).to_be_bytes())?;
+        dst.write_all(&payload)?;
+    }
+    Ok(())
+}
+
+/// Reverses [`compress_stream`]/[`compress_stream_with_limit`].
+pub fn extract_stream<R: Read, W: Write>(mut src: R, mut dst: W) -> io::Result<()> {
+    loop {
+        let mut header = [0u8; FRAME_HEADER_LEN];
+        let filled = read_fill(&mut src, &mut header)?;
+        if filled == 0 {
+            break;
+        }
+        if filled != FRAME_HEADER_LEN {
+            return Err(io::unexpected_eof());
+        }
+
+        let (magic, rest) = header.split_at(2);
+        if magic != MAGIC {
+            return Err(io::invalid_data("extract_stream: bad frame magic"));
+        }
+        if rest[0] != VERSION {
+            return Err(io::invalid_data("extract_stream: unsupported frame version"));
+        }
+        let payload_len = u32::from_be_bytes([rest[1], rest[2], rest[3], rest[4]]) as usize;
+
+        let mut payload = vec![0u8; payload_len];
+        src.read_exact(&mut payload)?;
+
+        let block = codec::extract_block(&payload)?;
+        dst.write_all(&block)?;
+    }
+    Ok(())
+}
+
+/// Fills `buf` from `src` with repeated `read` calls, stopping early
+/// only when a `read` returns `0` (end of stream). Returns the number
+/// of bytes actually filled, which is less than `buf.len()` only at
+/// the very end of `src`.
+fn read_fill<R: Read>(src: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        let n = src.read(&mut buf[filled..])?;
+        if n == 0 {
+            break;
+        }
+        filled += n;
+    }
+    Ok(filled)
+}